@@ -1,25 +1,54 @@
+use futures::future::{self, Either, Loop};
 use futures::Future;
-use hyper::client::connect::{Destination, HttpConnector};
-use std::net::ToSocketAddrs;
+use hyper::client::connect::{Connect, Destination, HttpConnector};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
+use tokio::timer::Delay;
 use tower_grpc::Request;
 use tower_hyper::{client, util};
 use tower_util::MakeService;
 
+mod output;
+mod tls;
+mod unix;
+
 pub mod node {
     include!(concat!(env!("OUT_DIR"), "/ensicoin_rpc.rs"));
 }
 
-use node::{Address, ConnectPeerRequest, DisconnectPeerRequest, GetInfoRequest, Peer};
+use node::{
+    Address, ConnectPeerRequest, DisconnectPeerRequest, GetInfoRequest, GetPeersRequest, Peer,
+};
+use output::OutputFormat;
+use tls::MaybeTlsConnector;
+use unix::UnixConnector;
 
 #[derive(StructOpt)]
 #[structopt(name = "arc-cli", about = "A CLI to use with an ensicoin node")]
 struct Config {
     #[structopt(
-        about = "The address of the local node",
+        about = "The address of the local node (http://, https://, or unix:// for a local socket)",
         default_value = "http://localhost:4225"
     )]
     node_address: http::Uri,
+    #[structopt(
+        long = "ca-cert",
+        about = "A PEM certificate to trust in addition to the bundled webpki roots, for nodes behind a private CA"
+    )]
+    ca_cert: Option<PathBuf>,
+    #[structopt(
+        long = "ipv6",
+        about = "Prefer IPv6 addresses when resolving peer addresses"
+    )]
+    prefer_ipv6: bool,
+    #[structopt(
+        long = "output",
+        about = "How to render command output: human or json",
+        default_value = "human"
+    )]
+    output: OutputFormat,
     #[structopt(subcommand)]
     action: Action,
 }
@@ -28,62 +57,113 @@ struct Config {
 enum Action {
     #[structopt(about = "information on the node")]
     GetInfo,
-    #[structopt(about = "connect to another node")]
-    Connect { address: String },
+    #[structopt(about = "connect to one or more peers")]
+    Connect {
+        #[structopt(about = "peer addresses to connect to")]
+        addresses: Vec<String>,
+        #[structopt(
+            long = "from-file",
+            about = "also read peer addresses from this file, one per line"
+        )]
+        from_file: Option<PathBuf>,
+        #[structopt(
+            long = "retry",
+            about = "number of times to retry a peer that fails to connect",
+            default_value = "0"
+        )]
+        retry: u32,
+    },
     #[structopt(about = "disconnect from another node")]
     Disconnect { address: String },
+    #[structopt(about = "list connected peers")]
+    Peers,
+    #[structopt(about = "information on the node and its connected peers")]
+    Status,
 }
 
-fn find_ipv4(s: &str) -> Option<std::net::SocketAddr> {
-    s.to_socket_addrs().unwrap().find(|s| s.is_ipv4())
+/// Resolves a peer address, preferring one IP family but falling back to the
+/// other so IPv6-only hosts aren't silently dropped.
+fn resolve_peer_address(s: &str, prefer_ipv6: bool) -> Option<SocketAddr> {
+    let addrs: Vec<SocketAddr> = s.to_socket_addrs().ok()?.collect();
+    let (first, second): (fn(&SocketAddr) -> bool, fn(&SocketAddr) -> bool) = if prefer_ipv6 {
+        (SocketAddr::is_ipv6, SocketAddr::is_ipv4)
+    } else {
+        (SocketAddr::is_ipv4, SocketAddr::is_ipv6)
+    };
+    addrs
+        .iter()
+        .find(|a| first(a))
+        .or_else(|| addrs.iter().find(|a| second(a)))
+        .copied()
 }
 
-fn print_getinfo(
-    implementation: &str,
-    protocol_version: u32,
-    best_block_hash: &str,
-    genesis_hash: &str,
-) {
-    use yansi::Paint;
-    println!("{}", Paint::green("Node information").underline().bold());
-    println!("    {}", Paint::new("Node").underline().bold());
-    println!(
-        "        {}: {}",
-        Paint::new("Name").underline(),
-        implementation
-    );
-    println!(
-        "        {}: {}",
-        Paint::new("Protocol version").underline(),
-        protocol_version
-    );
-    println!("    {}", Paint::new("Chain").underline().bold());
-    println!(
-        "        {}: {}",
-        Paint::new("Best hash").underline(),
-        best_block_hash
-    );
-    println!(
-        "        {}: {}",
-        Paint::new("Genesis hash").underline(),
-        genesis_hash
-    );
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
-fn main() {
-    let config = Config::from_args();
+/// Connects to a single peer, retrying with exponential backoff on failure,
+/// and reports the final outcome through `output::print_peer_result`.
+fn connect_with_retry<T>(
+    client: node::client::Node<T>,
+    address: String,
+    socket_addr: SocketAddr,
+    retries: u32,
+    format: OutputFormat,
+) -> impl Future<Item = (), Error = ()>
+where
+    T: tower_grpc::client::GrpcService<tower_grpc::BoxBody> + Clone,
+    T::Future: Send + 'static,
+    T::ResponseBody: tower_grpc::Body + Send + 'static,
+    <T::ResponseBody as tower_grpc::Body>::Data: Send,
+{
+    future::loop_fn((client, 0u32), move |(mut client, attempt)| {
+        let peer = Peer {
+            address: Some(Address {
+                ip: format!("{}", socket_addr.ip()),
+                port: socket_addr.port() as u32,
+            }),
+        };
+        let address = address.clone();
+        client
+            .connect_peer(Request::new(ConnectPeerRequest { peer: Some(peer) }))
+            .then(move |result| match result {
+                Ok(_) => {
+                    output::print_peer_result(format, "connect", &address, Ok(()));
+                    Either::A(future::ok(Loop::Break(())))
+                }
+                Err(e) => {
+                    if attempt < retries {
+                        let wait = Duration::from_millis(200u64.saturating_mul(1 << attempt.min(10)));
+                        Either::B(
+                            Delay::new(Instant::now() + wait)
+                                .map_err(|_| ())
+                                .map(move |_| Loop::Continue((client, attempt + 1))),
+                        )
+                    } else {
+                        output::print_peer_result(format, "connect", &address, Err(e.to_string()));
+                        Either::A(future::ok(Loop::Break(())))
+                    }
+                }
+            })
+    })
+}
 
-    let uri: http::Uri = config.node_address;
-    let dst = match Destination::try_from_uri(uri.clone()) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Could not connect to {}: {}", uri, e);
-            return;
-        }
-    };
-    let connector = util::Connector::new(HttpConnector::new(4));
+/// Establishes the HTTP/2 connection over `connector` and dispatches
+/// `action` against it. Generic over the connector so the plain/TLS and the
+/// Unix-socket transports can share one dispatch path.
+fn run_action<C>(
+    connector: C,
+    origin: http::Uri,
+    dst: Destination,
+    action: Action,
+    prefer_ipv6: bool,
+    format: OutputFormat,
+) where
+    C: Connect + 'static,
+    C::Transport: Send + 'static,
+{
     let settings = client::Builder::new().http2_only(true).clone();
-    let mut make_client = client::Connect::with_builder(connector, settings);
+    let mut make_client = client::Connect::with_builder(util::Connector::new(connector), settings);
     let rg = make_client
         .make_service(dst)
         .map_err(|e| {
@@ -92,7 +172,7 @@ fn main() {
         .and_then(move |conn| {
             use node::client::Node;
             let conn = tower_request_modifier::Builder::new()
-                .set_origin(uri)
+                .set_origin(origin)
                 .build(conn)
                 .unwrap();
 
@@ -101,84 +181,187 @@ fn main() {
                 .map_err(|e| eprintln!("client closed: {}", e))
         });
 
-    match config.action {
+    match action {
         Action::GetInfo => {
             let info_req = rg.and_then(|mut client| {
                 client
                     .get_info(Request::new(GetInfoRequest {}))
                     .map_err(|e| eprintln!("Error retrieving information: {}", e))
-                    .and_then(|response| {
+                    .and_then(move |response| {
                         let response = response.into_inner();
-                        print_getinfo(
+                        output::print_getinfo(
+                            format,
                             &response.implementation,
                             response.protocol_version,
-                            &response
-                                .best_block_hash
-                                .iter()
-                                .map(|b| format!("{:02x}", b))
-                                .fold(String::new(), |mut acc, hb| {
-                                    acc.push_str(&hb);
-                                    acc
-                                }),
-                            &response
-                                .genesis_block_hash
-                                .iter()
-                                .map(|b| format!("{:02x}", b))
-                                .fold(String::new(), |mut acc, hb| {
-                                    acc.push_str(&hb);
-                                    acc
-                                }),
+                            &hex_string(&response.best_block_hash),
+                            &hex_string(&response.genesis_block_hash),
                         );
                         Ok(())
                     })
             });
             tokio::run(info_req);
         }
-        Action::Connect { address } => {
-            let socket_addr = match find_ipv4(&address) {
-                Some(a) => a,
-                None => {
-                    eprintln!("Could not resolve to ipv4");
-                    return;
-                }
-            };
-            let conn_req = rg.and_then(move |mut client| {
-                let address = Address {
-                    ip: format!("{}", socket_addr.ip()),
-                    port: socket_addr.port() as u32,
-                };
-                let peer = Peer {
-                    address: Some(address),
-                };
+        Action::Peers => {
+            let peers_req = rg.and_then(move |mut client| {
                 client
-                    .connect_peer(Request::new(ConnectPeerRequest { peer: Some(peer) }))
-                    .map_err(|e| eprintln!("Could not connect to peer: {}", e))
-                    .map(|_| ())
+                    .get_peers(Request::new(GetPeersRequest {}))
+                    .map_err(|e| eprintln!("Error retrieving peers: {}", e))
+                    .and_then(move |response| {
+                        output::print_peers(format, &response.into_inner().peers);
+                        Ok(())
+                    })
+            });
+            tokio::run(peers_req);
+        }
+        Action::Status => {
+            let status_req = rg.and_then(move |mut client| {
+                client
+                    .get_info(Request::new(GetInfoRequest {}))
+                    .map_err(|e| eprintln!("Error retrieving information: {}", e))
+                    .and_then(move |info_response| {
+                        client
+                            .get_peers(Request::new(GetPeersRequest {}))
+                            .map_err(|e| eprintln!("Error retrieving peers: {}", e))
+                            .map(move |peers_response| (info_response, peers_response))
+                    })
+                    .and_then(move |(info_response, peers_response)| {
+                        let info = info_response.into_inner();
+                        let peers = peers_response.into_inner().peers;
+                        output::print_status(
+                            format,
+                            &info.implementation,
+                            info.protocol_version,
+                            &hex_string(&info.best_block_hash),
+                            &hex_string(&info.genesis_block_hash),
+                            &peers,
+                        );
+                        Ok(())
+                    })
+            });
+            tokio::run(status_req);
+        }
+        Action::Connect {
+            mut addresses,
+            from_file,
+            retry,
+        } => {
+            if let Some(path) = from_file {
+                match std::fs::read_to_string(&path) {
+                    Ok(contents) => addresses.extend(
+                        contents
+                            .lines()
+                            .map(str::trim)
+                            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                            .map(str::to_string),
+                    ),
+                    Err(e) => {
+                        output::print_error(
+                            format,
+                            &format!("Could not read {}: {}", path.display(), e),
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            let mut resolved = Vec::new();
+            for address in addresses {
+                match resolve_peer_address(&address, prefer_ipv6) {
+                    Some(socket_addr) => resolved.push((address, socket_addr)),
+                    None => output::print_peer_result(
+                        format,
+                        "connect",
+                        &address,
+                        Err("could not resolve address".to_string()),
+                    ),
+                }
+            }
+
+            let conn_req = rg.and_then(move |client| {
+                future::join_all(resolved.into_iter().map(move |(address, socket_addr)| {
+                    connect_with_retry(client.clone(), address, socket_addr, retry, format)
+                }))
+                .map(|_| ())
             });
             tokio::run(conn_req)
         }
         Action::Disconnect { address } => {
-            let socket_addr = match find_ipv4(&address) {
+            let socket_addr = match resolve_peer_address(&address, prefer_ipv6) {
                 Some(a) => a,
                 None => {
-                    eprintln!("Could not resolve to ipv4");
-                    return;
+                    output::print_peer_result(
+                        format,
+                        "disconnect",
+                        &address,
+                        Err("could not resolve address".to_string()),
+                    );
+                    std::process::exit(1);
                 }
             };
             let conn_req = rg.and_then(move |mut client| {
-                let address = Address {
-                    ip: format!("{}", socket_addr.ip()),
-                    port: socket_addr.port() as u32,
-                };
                 let peer = Peer {
-                    address: Some(address),
+                    address: Some(Address {
+                        ip: format!("{}", socket_addr.ip()),
+                        port: socket_addr.port() as u32,
+                    }),
                 };
                 client
                     .disconnect_peer(Request::new(DisconnectPeerRequest { peer: Some(peer) }))
-                    .map_err(|e| eprintln!("Could not connect to peer: {}", e))
-                    .map(|_| ())
+                    .then(move |result| {
+                        let result = result.map(|_| ()).map_err(|e| e.to_string());
+                        output::print_peer_result(format, "disconnect", &address, result);
+                        Ok(())
+                    })
             });
             tokio::run(conn_req)
         }
     }
 }
+
+fn main() {
+    let config = Config::from_args();
+
+    let uri: http::Uri = config.node_address;
+    let prefer_ipv6 = config.prefer_ipv6;
+    let format = config.output;
+
+    if uri.scheme_str() == Some("unix") {
+        let path = std::path::PathBuf::from(uri.path());
+        let origin: http::Uri = "http://localhost".parse().unwrap();
+        let dst = Destination::try_from_uri(origin.clone()).unwrap();
+        run_action(
+            UnixConnector::new(path),
+            origin,
+            dst,
+            config.action,
+            prefer_ipv6,
+            format,
+        );
+        return;
+    }
+
+    let dst = match Destination::try_from_uri(uri.clone()) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("Could not connect to {}: {}", uri, e);
+            return;
+        }
+    };
+    match uri.scheme_str() {
+        Some("https") => {
+            let tls_config = match tls::build_client_config(config.ca_cert.as_deref()) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Could not load TLS configuration: {}", e);
+                    return;
+                }
+            };
+            let connector = MaybeTlsConnector::tls(HttpConnector::new(4), tls_config);
+            run_action(connector, uri, dst, config.action, prefer_ipv6, format);
+        }
+        _ => {
+            let connector = MaybeTlsConnector::plain(HttpConnector::new(4));
+            run_action(connector, uri, dst, config.action, prefer_ipv6, format);
+        }
+    }
+}
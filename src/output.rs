@@ -0,0 +1,188 @@
+use crate::node::Peer;
+use std::str::FromStr;
+
+/// How command results are rendered: colored text for a human at a terminal,
+/// or JSON objects, one per line, for scripts and monitoring (one object per
+/// command, except `connect`, which prints one result object per peer).
+#[derive(Clone, Copy)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(OutputFormat::Human),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!(
+                "unknown output format '{}', expected 'human' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+pub fn print_getinfo(
+    format: OutputFormat,
+    implementation: &str,
+    protocol_version: u32,
+    best_block_hash: &str,
+    genesis_hash: &str,
+) {
+    match format {
+        OutputFormat::Human => {
+            use yansi::Paint;
+            println!("{}", Paint::green("Node information").underline().bold());
+            println!("    {}", Paint::new("Node").underline().bold());
+            println!(
+                "        {}: {}",
+                Paint::new("Name").underline(),
+                implementation
+            );
+            println!(
+                "        {}: {}",
+                Paint::new("Protocol version").underline(),
+                protocol_version
+            );
+            println!("    {}", Paint::new("Chain").underline().bold());
+            println!(
+                "        {}: {}",
+                Paint::new("Best hash").underline(),
+                best_block_hash
+            );
+            println!(
+                "        {}: {}",
+                Paint::new("Genesis hash").underline(),
+                genesis_hash
+            );
+        }
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "implementation": implementation,
+                    "protocol_version": protocol_version,
+                    "best_block_hash": best_block_hash,
+                    "genesis_block_hash": genesis_hash,
+                })
+            );
+        }
+    }
+}
+
+fn peer_addresses(peers: &[Peer]) -> Vec<(&str, u32)> {
+    peers
+        .iter()
+        .filter_map(|p| p.address.as_ref())
+        .map(|a| (a.ip.as_str(), a.port))
+        .collect()
+}
+
+/// Formats an `ip:port` pair, bracketing the address when it's IPv6 so the
+/// host/port boundary stays unambiguous (`[::1]:4225`, not `::1:4225`).
+fn format_socket_addr(ip: &str, port: u32) -> String {
+    if ip.contains(':') {
+        format!("[{}]:{}", ip, port)
+    } else {
+        format!("{}:{}", ip, port)
+    }
+}
+
+pub fn print_peers(format: OutputFormat, peers: &[Peer]) {
+    match format {
+        OutputFormat::Human => {
+            use yansi::Paint;
+            println!("{}", Paint::green("Connected peers").underline().bold());
+            let addresses = peer_addresses(peers);
+            if addresses.is_empty() {
+                println!("    (none)");
+            }
+            for (ip, port) in addresses {
+                println!("    {}", format_socket_addr(ip, port));
+            }
+        }
+        OutputFormat::Json => {
+            let peers: Vec<_> = peer_addresses(peers)
+                .into_iter()
+                .map(|(ip, port)| serde_json::json!({"ip": ip, "port": port}))
+                .collect();
+            println!("{}", serde_json::json!({ "peers": peers }));
+        }
+    }
+}
+
+/// Combines `get_info` and the peer list into one aggregated view for the
+/// `status` subcommand.
+pub fn print_status(
+    format: OutputFormat,
+    implementation: &str,
+    protocol_version: u32,
+    best_block_hash: &str,
+    genesis_hash: &str,
+    peers: &[Peer],
+) {
+    match format {
+        OutputFormat::Human => {
+            print_getinfo(
+                format,
+                implementation,
+                protocol_version,
+                best_block_hash,
+                genesis_hash,
+            );
+            print_peers(format, peers);
+        }
+        OutputFormat::Json => {
+            let peers: Vec<_> = peer_addresses(peers)
+                .into_iter()
+                .map(|(ip, port)| serde_json::json!({"ip": ip, "port": port}))
+                .collect();
+            println!(
+                "{}",
+                serde_json::json!({
+                    "implementation": implementation,
+                    "protocol_version": protocol_version,
+                    "best_block_hash": best_block_hash,
+                    "genesis_block_hash": genesis_hash,
+                    "peers": peers,
+                })
+            );
+        }
+    }
+}
+
+/// Reports a fatal, pre-connection error (e.g. a bad `--from-file` path) in
+/// the selected format. The caller is responsible for exiting with a
+/// non-zero status so scripts can detect the failure.
+pub fn print_error(format: OutputFormat, message: &str) {
+    match format {
+        OutputFormat::Human => eprintln!("{}", message),
+        OutputFormat::Json => {
+            println!("{}", serde_json::json!({"success": false, "error": message}));
+        }
+    }
+}
+
+/// Reports the outcome of a connect/disconnect call: silent on success and
+/// an `eprintln!` on failure in human mode, a JSON result object in json mode.
+pub fn print_peer_result(format: OutputFormat, action: &str, address: &str, result: Result<(), String>) {
+    match (format, result) {
+        (OutputFormat::Human, Ok(())) => {}
+        (OutputFormat::Human, Err(e)) => eprintln!("Could not {} peer: {}", action, e),
+        (OutputFormat::Json, Ok(())) => {
+            println!(
+                "{}",
+                serde_json::json!({"action": action, "address": address, "success": true})
+            );
+        }
+        (OutputFormat::Json, Err(e)) => {
+            println!(
+                "{}",
+                serde_json::json!({"action": action, "address": address, "success": false, "error": e})
+            );
+        }
+    }
+}
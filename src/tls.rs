@@ -0,0 +1,170 @@
+use futures::{Future, Poll};
+use hyper::client::connect::{Connect, Connected, Destination, HttpConnector};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsConnector;
+use webpki::DNSNameRef;
+
+/// A trait object can only have one non-auto principal trait, so `AsyncRead
+/// + AsyncWrite` can't be combined directly in a `dyn` type. `ErasedIo` is
+/// that combination as a single named trait, with a blanket impl so any
+/// transport satisfies it for free.
+pub trait ErasedIo: AsyncRead + AsyncWrite + Send {}
+impl<T: AsyncRead + AsyncWrite + Send> ErasedIo for T {}
+
+/// A single boxed transport so `MaybeTlsConnector` can return the same
+/// `Transport` type whether or not the connection ends up TLS-wrapped.
+type BoxedIo = Box<dyn ErasedIo>;
+
+fn boxed_io<T: AsyncRead + AsyncWrite + Send + 'static>(io: T) -> BoxedIo {
+    Box::new(io)
+}
+
+impl io::Read for Box<dyn ErasedIo> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl io::Write for Box<dyn ErasedIo> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl AsyncRead for Box<dyn ErasedIo> {}
+
+impl AsyncWrite for Box<dyn ErasedIo> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        (**self).shutdown()
+    }
+}
+
+/// Builds the `rustls::ClientConfig` used for `https://` node addresses.
+///
+/// Trusts the bundled webpki roots by default, plus whatever PEM certificate
+/// is passed via `--ca-cert` (for self-hosted nodes behind a private CA).
+pub fn build_client_config(ca_cert: Option<&Path>) -> io::Result<Arc<rustls::ClientConfig>> {
+    let mut config = rustls::ClientConfig::new();
+    config
+        .root_store
+        .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+
+    if let Some(path) = ca_cert {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let (added, ignored) = config
+            .root_store
+            .add_pem_file(&mut reader)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid CA certificate"))?;
+        if added == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no certificates found in {}", path.display()),
+            ));
+        }
+        let _ = ignored;
+    }
+
+    config.alpn_protocols.push(b"h2".to_vec());
+    Ok(Arc::new(config))
+}
+
+/// Connects over TLS on top of a plain `HttpConnector`, using the connection
+/// authority for SNI.
+pub struct HttpsConnector {
+    http: HttpConnector,
+    tls: TlsConnector,
+}
+
+impl HttpsConnector {
+    pub fn new(mut http: HttpConnector, config: Arc<rustls::ClientConfig>) -> Self {
+        // `HttpConnector` rejects any `Destination` whose scheme isn't
+        // `http` before it opens a socket; node addresses we TLS-wrap carry
+        // scheme `https`, so enforcement has to be turned off here.
+        http.enforce_http(false);
+        HttpsConnector {
+            http,
+            tls: TlsConnector::from(config),
+        }
+    }
+}
+
+impl Connect for HttpsConnector {
+    type Transport = tokio_rustls::client::TlsStream<<HttpConnector as Connect>::Transport>;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Self::Transport, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        let host = dst.host().to_string();
+        let tls = self.tls.clone();
+        let fut = self
+            .http
+            .connect(dst)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+            .and_then(move |(tcp, connected)| {
+                let name = DNSNameRef::try_from_ascii_str(&host).map_err(|_| {
+                    if host.parse::<std::net::IpAddr>().is_ok() {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!(
+                                "cannot use TLS with an IP literal node address ({}): rustls requires a DNS name for SNI, use a hostname or connect over http://",
+                                host
+                            ),
+                        )
+                    } else {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("invalid DNS name in node address: {}", host),
+                        )
+                    }
+                })?;
+                Ok(tls.connect(name, tcp).map(|stream| (stream, connected)))
+            })
+            .flatten();
+        Box::new(fut)
+    }
+}
+
+/// Either the plaintext or the TLS connector, chosen in `main` from the node
+/// address scheme, so both network paths share one `Connect` implementation.
+pub enum MaybeTlsConnector {
+    Plain(HttpConnector),
+    Tls(HttpsConnector),
+}
+
+impl MaybeTlsConnector {
+    pub fn plain(http: HttpConnector) -> Self {
+        MaybeTlsConnector::Plain(http)
+    }
+
+    pub fn tls(http: HttpConnector, config: Arc<rustls::ClientConfig>) -> Self {
+        MaybeTlsConnector::Tls(HttpsConnector::new(http, config))
+    }
+}
+
+impl Connect for MaybeTlsConnector {
+    type Transport = BoxedIo;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (Self::Transport, Connected), Error = io::Error> + Send>;
+
+    fn connect(&self, dst: Destination) -> Self::Future {
+        match self {
+            MaybeTlsConnector::Plain(http) => Box::new(
+                http.connect(dst)
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+                    .map(|(io, connected)| (boxed_io(io), connected)),
+            ),
+            MaybeTlsConnector::Tls(https) => {
+                Box::new(https.connect(dst).map(|(io, connected)| (boxed_io(io), connected)))
+            }
+        }
+    }
+}